@@ -0,0 +1,117 @@
+//! Orbit/arcball camera controller: rotates around a focus point using mouse
+//! drag and scroll, as an alternative to the WASD free-fly controller in
+//! [`crate::update`]. Both controllers write the same "camera" object's
+//! transform (this one only on frames with a drag or scroll), so either input
+//! scheme works interchangeably.
+
+use r3d::{
+    input::InputDevice,
+    math::{Mat4, Vec3},
+    transform::{Transform, TransformComponent},
+    use_context,
+};
+
+const MIN_PITCH: f32 = -89.0f32.to_radians();
+const MAX_PITCH: f32 = 89.0f32.to_radians();
+const MIN_RADIUS: f32 = 0.5;
+const MAX_RADIUS: f32 = 20.0;
+const ORBIT_SENSITIVITY: f32 = 0.005;
+const PAN_SENSITIVITY: f32 = 0.0025;
+const ZOOM_SENSITIVITY: f32 = 0.5;
+
+/// Spherical orbit state around `target`: `eye = target + radius * dir(yaw, pitch)`.
+pub struct OrbitCamera {
+    target: Vec3,
+    yaw: f32,
+    pitch: f32,
+    radius: f32,
+}
+
+impl OrbitCamera {
+    /// Derives the initial yaw/pitch/radius from an existing look-at pair, so
+    /// the orbit camera starts exactly where the free-fly camera left off.
+    pub fn new(eye: Vec3, target: Vec3) -> Self {
+        Self::from_eye_and_target(eye, target)
+    }
+
+    fn from_eye_and_target(eye: Vec3, target: Vec3) -> Self {
+        let offset = eye - target;
+        let radius = offset.length().max(MIN_RADIUS);
+        let pitch = (offset.y / radius).clamp(-1.0, 1.0).asin();
+        let yaw = offset.z.atan2(offset.x);
+
+        OrbitCamera {
+            target,
+            yaw,
+            pitch,
+            radius,
+        }
+    }
+
+    /// Re-derives yaw/pitch/radius/target from the camera's current transform,
+    /// keeping the existing `radius` as the assumed look-at distance (the
+    /// transform alone only gives us the eye position and facing direction,
+    /// not a focus point). Called every frame, even idle ones, so that if the
+    /// free-fly controller in [`crate::update`] has been moving the camera,
+    /// this controller picks up from there instead of snapping back to
+    /// wherever it last left the camera.
+    fn resync(&mut self, camera_transform: &TransformComponent) {
+        let eye = camera_transform.position();
+        let target = eye + camera_transform.forward() * self.radius;
+        *self = Self::from_eye_and_target(eye, target);
+    }
+
+    /// Reads mouse delta/scroll for this frame and, only if a drag or scroll
+    /// actually happened, re-derives the camera transform from the updated
+    /// spherical coordinates. Staying a no-op otherwise means this controller
+    /// can sit alongside the WASD free-fly controller in [`crate::update`]
+    /// without either one fighting the other for the camera transform.
+    pub fn update(&mut self) {
+        let ctx = use_context();
+        let camera_object = ctx.object_mgr().find("camera").unwrap();
+        let camera_transform = camera_object.component::<TransformComponent>();
+        self.resync(&camera_transform);
+
+        let input_mgr = ctx.input_mgr();
+        let mouse = input_mgr.mouse();
+
+        let delta_x = mouse.input("delta_x").unwrap().value;
+        let delta_y = mouse.input("delta_y").unwrap().value;
+        let scroll_y = mouse.input("scroll_y").unwrap().value;
+        let left_button = mouse.input("left").unwrap().value;
+        let middle_button = mouse.input("middle").unwrap().value;
+
+        let dragging = left_button > 0.0 || middle_button > 0.0;
+        let scrolling = scroll_y != 0.0;
+        if !dragging && !scrolling {
+            return;
+        }
+
+        if left_button > 0.0 {
+            self.yaw -= delta_x * ORBIT_SENSITIVITY;
+            self.pitch = (self.pitch + delta_y * ORBIT_SENSITIVITY).clamp(MIN_PITCH, MAX_PITCH);
+        }
+
+        if middle_button > 0.0 {
+            let forward = (self.target - self.eye()).normalized();
+            let right = forward.cross(Vec3::UP).normalized();
+            let up = right.cross(forward);
+            self.target += (-delta_x * right + delta_y * up) * PAN_SENSITIVITY * self.radius;
+        }
+
+        self.radius = (self.radius - scroll_y * ZOOM_SENSITIVITY).clamp(MIN_RADIUS, MAX_RADIUS);
+
+        let eye = self.eye();
+        let transform = Transform::from_mat4(&Mat4::look_at(eye, self.target, Vec3::UP));
+        camera_transform.set_transform(transform);
+    }
+
+    fn eye(&self) -> Vec3 {
+        let direction = Vec3::new(
+            self.pitch.cos() * self.yaw.cos(),
+            self.pitch.sin(),
+            self.pitch.cos() * self.yaw.sin(),
+        );
+        self.target + direction * self.radius
+    }
+}
@@ -0,0 +1,64 @@
+//! Batches rigid (non-skinned) mesh parts that share a `(mesh, material)`
+//! pair behind one [`InstancedMeshRenderer`], so e.g. several nodes that place
+//! the same mesh with the same material draw in a single `draw_indexed` call
+//! instead of one [`MeshRenderer`](r3d::gfx::MeshRenderer) per part. Parts with
+//! different meshes always land in different batches.
+
+use r3d::{
+    gfx::{InstanceId, InstancedMeshRenderer, MaterialHandle, MeshHandle},
+    math::Mat4,
+    object::ObjectHandle,
+    specs::Builder,
+    use_context,
+    wgpu::Device,
+};
+use std::collections::HashMap;
+
+/// Owns one [`InstancedMeshRenderer`] per distinct `(mesh_index, material_index)`
+/// pair seen so far, keyed by the same assimp indices `deploy_parts` already
+/// uses to look meshes and materials up.
+#[derive(Default)]
+pub struct InstanceBatches {
+    batch_objects: HashMap<(u32, u32), ObjectHandle>,
+}
+
+impl InstanceBatches {
+    pub fn new() -> Self {
+        InstanceBatches::default()
+    }
+
+    /// Adds one instance at `transform` to the batch for `mesh_index`/
+    /// `material_index`, creating that batch's object and
+    /// [`InstancedMeshRenderer`] the first time the pair is seen.
+    pub fn push_instance(
+        &mut self,
+        device: &Device,
+        mesh_index: u32,
+        material_index: u32,
+        mesh: MeshHandle,
+        material: MaterialHandle,
+        transform: Mat4,
+    ) -> InstanceId {
+        let key = (mesh_index, material_index);
+
+        if let std::collections::hash_map::Entry::Vacant(entry) = self.batch_objects.entry(key) {
+            let ctx = use_context();
+            let mut world = ctx.world_mut();
+            let mut object_mgr = ctx.object_mgr_mut();
+            let (object, builder) = object_mgr.create_object_builder(
+                &mut world,
+                Some(format!("instance-batch-{}-{}", mesh_index, material_index)),
+                None,
+            );
+            builder
+                .with(InstancedMeshRenderer::new(mesh, material, device))
+                .build();
+            entry.insert(object);
+        }
+
+        let object = self.batch_objects.get(&key).unwrap();
+        object
+            .component::<InstancedMeshRenderer>()
+            .push_instance(transform, device)
+    }
+}
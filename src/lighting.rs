@@ -0,0 +1,111 @@
+//! A single scene light and the Blinn-Phong lit material variant that samples
+//! it. Keeping this separate from [`material_import`](crate::material_import)
+//! means the plain textured (skinned) materials don't pay for bindings they don't use.
+
+use crate::shadow::ShadowMap;
+use bytemuck::{Pod, Zeroable};
+use r3d::{
+    gfx::{
+        BindGroupEntryResource, BindingPropKey, Material, MaterialHandle, ShaderHandle, Texture,
+    },
+    math::Vec3,
+    wgpu::{self, util::DeviceExt},
+    ContextHandle,
+};
+
+/// A directional light (e.g. the sun, infinitely far away) or a point light
+/// (e.g. a lamp, radiating from a world position).
+pub enum Light {
+    Directional { direction: Vec3, color: Vec3 },
+    Point { position: Vec3, color: Vec3 },
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct LightUniform {
+    position_or_direction: [f32; 3],
+    is_point: f32,
+    color: [f32; 3],
+    _padding: f32,
+}
+
+impl Light {
+    fn to_uniform(&self) -> LightUniform {
+        match *self {
+            Light::Directional { direction, color } => LightUniform {
+                position_or_direction: [direction.x, direction.y, direction.z],
+                is_point: 0.0,
+                color: [color.x, color.y, color.z],
+                _padding: 0.0,
+            },
+            Light::Point { position, color } => LightUniform {
+                position_or_direction: [position.x, position.y, position.z],
+                is_point: 1.0,
+                color: [color.x, color.y, color.z],
+                _padding: 0.0,
+            },
+        }
+    }
+
+    pub fn create_buffer(&self, device: &wgpu::Device) -> wgpu::Buffer {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("light"),
+            contents: bytemuck::bytes_of(&self.to_uniform()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        })
+    }
+}
+
+/// Builds a material bound to the lit `shader`, exposing the diffuse
+/// texture/sampler pair, the `light` uniform the Blinn-Phong fragment shader
+/// reads, and the shadow map it attenuates by. The camera's world position is
+/// supplied directly by the `CameraPosition` camera binding, not this material.
+pub fn create_lit_material(
+    ctx: &ContextHandle,
+    shader: &ShaderHandle,
+    texture: &Texture,
+    light_buffer: &wgpu::Buffer,
+    shadow_map: &ShadowMap,
+    light_view_proj_buffer: &wgpu::Buffer,
+) -> MaterialHandle {
+    let mut render_mgr = ctx.render_mgr_mut();
+    let mut material = Material::new(shader.clone(), render_mgr.pipeline_layout_cache());
+    material.set_bind_property(
+        &BindingPropKey::StringKey("texture".to_owned()),
+        BindGroupEntryResource::TextureView {
+            texture_view: texture.view.clone(),
+        },
+    );
+    material.set_bind_property(
+        &BindingPropKey::StringKey("texture_sampler".to_owned()),
+        BindGroupEntryResource::Sampler {
+            sampler: texture.sampler.clone(),
+        },
+    );
+    material.set_bind_property(
+        &BindingPropKey::StringKey("light".to_owned()),
+        BindGroupEntryResource::Buffer {
+            buffer: light_buffer.clone(),
+        },
+    );
+    material.set_bind_property(
+        &BindingPropKey::StringKey("shadow_map".to_owned()),
+        BindGroupEntryResource::TextureView {
+            texture_view: shadow_map.depth_view.clone(),
+        },
+    );
+    material.set_bind_property(
+        &BindingPropKey::StringKey("shadow_sampler".to_owned()),
+        BindGroupEntryResource::Sampler {
+            sampler: shadow_map.comparison_sampler.clone(),
+        },
+    );
+    material.set_bind_property(
+        &BindingPropKey::StringKey("light_view_proj".to_owned()),
+        BindGroupEntryResource::Buffer {
+            buffer: light_view_proj_buffer.clone(),
+        },
+    );
+    material.update_bind_group(&ctx.gfx_ctx().device);
+    MaterialHandle::new(material)
+}
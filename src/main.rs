@@ -1,12 +1,24 @@
+mod camera_controller;
+mod instancing;
+mod lighting;
+mod material_import;
+mod picking;
+mod shadow;
+mod skinning;
+
+use camera_controller::OrbitCamera;
+use instancing::InstanceBatches;
+use lighting::{create_lit_material, Light};
+use material_import::{create_textured_material, import_material_images, upload_material_textures};
+use picking::{PickableMesh, PickingController};
 use pollster::FutureExt;
 use r3d::{
     event::{event_types, EventHandler},
     gfx::{
-        BindGroupEntryResource, BindingPropKey, Camera, CameraClearMode,
-        CameraPerspectiveProjection, CameraPerspectiveProjectionAspect, CameraProjection, Color,
-        Material, MaterialHandle, Mesh, MeshHandle, MeshRenderer, ShaderHandle, Texture,
+        Camera, CameraClearMode, CameraPerspectiveProjection, CameraPerspectiveProjectionAspect,
+        CameraProjection, Color, MaterialHandle, Mesh, MeshHandle, ShaderHandle,
+        SkinnedMeshRenderer,
     },
-    image,
     input::InputDevice,
     math::{Mat4, Quat, Vec3},
     object::ObjectHandle,
@@ -14,10 +26,14 @@ use r3d::{
     specs::Builder,
     transform::{Transform, TransformComponent},
     use_context,
-    wgpu::{Device, TextureFormat},
+    wgpu::Device,
     ContextHandle, Engine, EngineConfig, EngineExecError, EngineInitError, EngineLoopMode,
     EngineTargetFps,
 };
+use shadow::{
+    create_light_view_proj_buffer, create_shadow_map, register_depth_prepass, ShadowConfig,
+};
+use skinning::{build_vertex_skin, update_skinned_renderers, SkinBones};
 use std::{collections::HashMap, path::Path};
 use thiserror::Error;
 
@@ -83,9 +99,22 @@ fn init(ctx: ContextHandle) {
             .create_shader(
                 ctx.render_mgr_mut().bind_group_layout_cache(),
                 "
-@group(0) @binding(0) var<uniform> camera_transform: mat4x4<f32>;
+@group(0) @binding(0) var<uniform> camera_view_proj: mat4x4<f32>;
+@group(0) @binding(2) var<uniform> camera_position: vec3<f32>;
 @group(1) @binding(0) var texture: texture_2d<f32>;
 @group(1) @binding(1) var texture_sampler: sampler;
+@group(1) @binding(2) var<uniform> light: Light;
+@group(1) @binding(3) var shadow_map: texture_depth_2d;
+@group(1) @binding(4) var shadow_sampler: sampler_comparison;
+@group(1) @binding(5) var<uniform> light_view_proj: mat4x4<f32>;
+
+const SHADOW_DEPTH_BIAS: f32 = 0.002;
+
+struct Light {
+    position_or_direction: vec3<f32>,
+    is_point: f32,
+    color: vec3<f32>,
+};
 
 struct InstanceInput {
     @location(0) transform_row_0: vec4<f32>,
@@ -97,11 +126,14 @@ struct InstanceInput {
 struct VertexInput {
     @location(4) position: vec3<f32>,
     @location(5) uv: vec2<f32>,
+    @location(6) normal: vec3<f32>,
 };
 
 struct VertexOutput {
     @builtin(position) position: vec4<f32>,
     @location(0) uv: vec2<f32>,
+    @location(1) world_position: vec3<f32>,
+    @location(2) world_normal: vec3<f32>,
 };
 
 struct FragmentOutput {
@@ -112,136 +144,119 @@ struct FragmentOutput {
 fn vs_main(instance: InstanceInput, vertex: VertexInput) -> VertexOutput {
     var out: VertexOutput;
     let transform = mat4x4<f32>(instance.transform_row_0, instance.transform_row_1, instance.transform_row_2, instance.transform_row_3);
-    out.position = vec4<f32>(camera_transform * transform * vec4<f32>(vertex.position, 1.0));
+    let world_position = transform * vec4<f32>(vertex.position, 1.0);
+
+    out.position = camera_view_proj * world_position;
     out.uv = vertex.uv;
+    out.world_position = world_position.xyz;
+    // Assumes the instance transform has no non-uniform scale; a dedicated
+    // inverse-transpose normal matrix isn't plumbed through per-instance data yet.
+    out.world_normal = normalize((transform * vec4<f32>(vertex.normal, 0.0)).xyz);
     return out;
 }
 
 @fragment
 fn fs_main(in: VertexOutput) -> FragmentOutput {
     var out: FragmentOutput;
-    out.color = textureSample(texture, texture_sampler, in.uv);
+
+    let base_color = textureSample(texture, texture_sampler, in.uv);
+    let normal = normalize(in.world_normal);
+    let light_dir = normalize(select(
+        -light.position_or_direction,
+        light.position_or_direction - in.world_position,
+        light.is_point != 0.0,
+    ));
+    let view_dir = normalize(camera_position - in.world_position);
+    let half_dir = normalize(light_dir + view_dir);
+
+    let ambient = 0.1 * light.color;
+    let diffuse = max(dot(normal, light_dir), 0.0) * light.color;
+    let specular = pow(max(dot(normal, half_dir), 0.0), 32.0) * light.color;
+
+    var shadow = 1.0;
+    let light_clip = light_view_proj * vec4<f32>(in.world_position, 1.0);
+    if (light_clip.w > 0.0) {
+        let light_ndc = light_clip.xyz / light_clip.w;
+        let in_frustum = light_ndc.x >= -1.0 && light_ndc.x <= 1.0
+            && light_ndc.y >= -1.0 && light_ndc.y <= 1.0
+            && light_ndc.z >= 0.0 && light_ndc.z <= 1.0;
+        if (in_frustum) {
+            let shadow_uv = vec2<f32>(light_ndc.x * 0.5 + 0.5, 0.5 - light_ndc.y * 0.5);
+            shadow = textureSampleCompare(shadow_map, shadow_sampler, shadow_uv, light_ndc.z - SHADOW_DEPTH_BIAS);
+        }
+    }
+
+    out.color = vec4<f32>((ambient + shadow * (diffuse + specular)) * base_color.rgb, base_color.a);
     return out;
 }",
             )
             .unwrap();
-    let mut materials = HashMap::new();
-
-    fn create_textured_material(
-        ctx: &ContextHandle,
-        shader: &ShaderHandle,
-        path: impl AsRef<Path>,
-    ) -> MaterialHandle {
-        let mut render_mgr = ctx.render_mgr_mut();
-        let mut material = Material::new(shader.clone(), render_mgr.pipeline_layout_cache());
-        let texture = Texture::from_image(
-            TextureFormat::Rgba8UnormSrgb,
-            &image::open(path).unwrap().flipv(),
-            &ctx.gfx_ctx().device,
-            &ctx.gfx_ctx().queue,
-        );
-        material.set_bind_property(
-            &BindingPropKey::StringKey("texture".to_owned()),
-            BindGroupEntryResource::TextureView {
-                texture_view: texture.view.clone(),
-            },
-        );
-        material.set_bind_property(
-            &BindingPropKey::StringKey("texture_sampler".to_owned()),
-            BindGroupEntryResource::Sampler {
-                sampler: texture.sampler.clone(),
-            },
-        );
-        material.update_bind_group(&ctx.gfx_ctx().device);
-        MaterialHandle::new(material)
-    }
 
-    materials.insert(
-        "Body",
-        create_textured_material(
-            &ctx,
-            &shader,
-            // "/Users/ashrimp/Downloads/Karin Body&Face Textures/Karin_Face_Tex.png",
-            "/Users/ashrimp/Downloads/Karin_v1.11/Textures/Karin_Face.png",
-        ),
-    );
-    materials.insert(
-        "body_2",
-        create_textured_material(
-            &ctx,
-            &shader,
-            // "/Users/ashrimp/Downloads/Karin Body&Face Textures/Karin_Body_Tex.png",
-            "/Users/ashrimp/Downloads/Karin_v1.11/Textures/Karin_Body.png",
-        ),
-    );
-    materials.insert(
-        "knee-socks",
-        create_textured_material(
-            &ctx,
-            &shader,
-            "/Users/ashrimp/Downloads/Karin_v1.11/Textures/Karin_Costume.png",
-        ),
-    );
-    materials.insert(
-        "hair",
-        create_textured_material(
-            &ctx,
-            &shader,
-            "/Users/ashrimp/Downloads/Karin_v1.11/Textures/Karin_Hair.png",
-        ),
-    );
-    materials.insert(
-        "kemomimi",
-        create_textured_material(
-            &ctx,
-            &shader,
-            "/Users/ashrimp/Downloads/Karin_v1.11/Textures/Karin_Hair.png",
-        ),
-    );
-    materials.insert(
-        "tail",
-        create_textured_material(
-            &ctx,
-            &shader,
-            "/Users/ashrimp/Downloads/Karin_v1.11/Textures/Karin_Hair.png",
-        ),
-    );
-    materials.insert(
-        "pullover",
-        create_textured_material(
-            &ctx,
-            &shader,
-            "/Users/ashrimp/Downloads/Karin_v1.11/Textures/Karin_Costume.png",
-        ),
-    );
-    materials.insert(
-        "shoes",
-        create_textured_material(
-            &ctx,
-            &shader,
-            "/Users/ashrimp/Downloads/Karin_v1.11/Textures/Karin_Costume.png",
-        ),
-    );
-    materials.insert(
-        "skirt",
-        create_textured_material(
-            &ctx,
-            &shader,
-            "/Users/ashrimp/Downloads/Karin_v1.11/Textures/Karin_Costume.png",
-        ),
-    );
-    materials.insert(
-        "underwear",
-        create_textured_material(
-            &ctx,
-            &shader,
-            "/Users/ashrimp/Downloads/Karin_v1.11/Textures/Karin_Costume.png",
-        ),
-    );
+    let skinned_shader = ctx
+            .shader_mgr()
+            .create_shader(
+                ctx.render_mgr_mut().bind_group_layout_cache(),
+                "
+@group(0) @binding(0) var<uniform> camera_view_proj: mat4x4<f32>;
+@group(1) @binding(0) var texture: texture_2d<f32>;
+@group(1) @binding(1) var texture_sampler: sampler;
+@group(2) @binding(0) var<uniform> bone_palette: array<mat4x4<f32>, 128>;
+
+struct InstanceInput {
+    @location(0) transform_row_0: vec4<f32>,
+    @location(1) transform_row_1: vec4<f32>,
+    @location(2) transform_row_2: vec4<f32>,
+    @location(3) transform_row_3: vec4<f32>,
+};
+
+struct VertexInput {
+    @location(4) position: vec3<f32>,
+    @location(5) uv: vec2<f32>,
+    @location(6) bone_indices: vec4<u32>,
+    @location(7) bone_weights: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
 
+struct FragmentOutput {
+    @location(0) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(instance: InstanceInput, vertex: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+
+    // `bone_palette` entries are bone world matrices (see `update_skinned_renderers`),
+    // so `skinned_position` already lands in world space; the per-instance
+    // `transform` the rigid pipeline applies here would place the mesh node's
+    // own world transform on top a second time.
+    let local_position = vec4<f32>(vertex.position, 1.0);
+    var skinned_position = vec4<f32>(0.0, 0.0, 0.0, 0.0);
+    skinned_position = skinned_position + vertex.bone_weights.x * (bone_palette[vertex.bone_indices.x] * local_position);
+    skinned_position = skinned_position + vertex.bone_weights.y * (bone_palette[vertex.bone_indices.y] * local_position);
+    skinned_position = skinned_position + vertex.bone_weights.z * (bone_palette[vertex.bone_indices.z] * local_position);
+    skinned_position = skinned_position + vertex.bone_weights.w * (bone_palette[vertex.bone_indices.w] * local_position);
+
+    out.position = camera_view_proj * skinned_position;
+    out.uv = vertex.uv;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> FragmentOutput {
+    var out: FragmentOutput;
+    out.color = textureSample(texture, texture_sampler, in.uv);
+    return out;
+}",
+            )
+            .unwrap();
+
+    let model_path = Path::new("/Users/ashrimp/Downloads/Karin_v1.11/FBX/Karin_ver1.1.1.fbx");
     let scene = {
-        let file =
-            std::fs::read("/Users/ashrimp/Downloads/Karin_v1.11/FBX/Karin_ver1.1.1.fbx").unwrap();
+        let file = std::fs::read(model_path).unwrap();
         russimp::scene::Scene::from_buffer(
             &file,
             vec![
@@ -255,26 +270,118 @@ fn fs_main(in: VertexOutput) -> FragmentOutput {
         )
         .unwrap()
     };
-    let meshes = HashMap::from_iter(
+    let model_dir = model_path.parent().unwrap_or_else(|| Path::new("."));
+
+    let light_direction = Vec3::new(-0.4, -1.0, -0.3).normalized();
+    let light = Light::Directional {
+        direction: light_direction,
+        color: Vec3::new(1.0, 1.0, 1.0),
+    };
+    let light_buffer = light.create_buffer(&ctx.gfx_ctx().device);
+
+    let shadow_config = ShadowConfig::default();
+    let shadow_map = create_shadow_map(
+        &ctx.gfx_ctx().device,
+        &shadow_config,
+        light_direction,
+        target,
+    );
+    register_depth_prepass(&ctx, &shadow_map);
+    let light_view_proj_buffer =
+        create_light_view_proj_buffer(&ctx.gfx_ctx().device, shadow_map.light_view_proj);
+
+    let material_images = import_material_images(&scene, model_dir);
+    let material_textures = upload_material_textures(&ctx, &material_images);
+    let materials: HashMap<u32, MaterialHandle> = material_textures
+        .iter()
+        .map(|(&index, texture)| {
+            let material = create_lit_material(
+                &ctx,
+                &shader,
+                texture,
+                &light_buffer,
+                &shadow_map,
+                &light_view_proj_buffer,
+            );
+            (index, material)
+        })
+        .collect();
+    let skinned_materials: HashMap<u32, MaterialHandle> = material_textures
+        .iter()
+        .map(|(&index, texture)| {
+            (
+                index,
+                create_textured_material(&ctx, &skinned_shader, texture),
+            )
+        })
+        .collect();
+    let mesh_material_indices = HashMap::<u32, u32>::from_iter(
         scene
             .meshes
-            .into_iter()
+            .iter()
             .enumerate()
-            .map(|(index, mesh)| (index as u32, MeshHandle::new(Mesh { data: mesh }))),
+            .map(|(index, mesh)| (index as u32, mesh.material_index)),
     );
+    let mut meshes = HashMap::new();
+    let mut mesh_skins = HashMap::<u32, SkinBones>::new();
+    let mut mesh_geometry = HashMap::<u32, (Vec<Vec3>, Vec<[u32; 3]>)>::new();
+    for (index, mesh) in scene.meshes.into_iter().enumerate() {
+        let index = index as u32;
+        let vertex_skin = match build_vertex_skin(&mesh) {
+            Some((vertex_skin, bones)) => {
+                mesh_skins.insert(index, bones);
+                Some(vertex_skin)
+            }
+            None => None,
+        };
+        mesh_geometry.insert(
+            index,
+            (
+                mesh.vertices
+                    .iter()
+                    .map(|vertex| Vec3::new(vertex.x, vertex.y, vertex.z))
+                    .collect(),
+                mesh.faces
+                    .iter()
+                    .map(|face| [face.0[0], face.0[1], face.0[2]])
+                    .collect(),
+            ),
+        );
+        meshes.insert(
+            index,
+            MeshHandle::new(Mesh {
+                data: mesh,
+                skin: vertex_skin,
+            }),
+        );
+    }
 
     fn deploy_parts(
         device: &Device,
-        materials: &HashMap<&str, MaterialHandle>,
+        materials: &HashMap<u32, MaterialHandle>,
+        skinned_materials: &HashMap<u32, MaterialHandle>,
+        mesh_material_indices: &HashMap<u32, u32>,
+        mesh_skins: &HashMap<u32, SkinBones>,
         meshes: &HashMap<u32, MeshHandle>,
+        skinned_entries: &mut Vec<(ObjectHandle, SkinBones)>,
+        rigid_batch_entries: &mut Vec<(ObjectHandle, u32, u32)>,
+        pickable_entries: &mut Vec<(ObjectHandle, u32)>,
         node: &Node,
     ) -> ObjectHandle {
-        let children = Vec::from_iter(
-            node.children
-                .borrow()
-                .iter()
-                .map(|child| deploy_parts(device, materials, meshes, child)),
-        );
+        let children = Vec::from_iter(node.children.borrow().iter().map(|child| {
+            deploy_parts(
+                device,
+                materials,
+                skinned_materials,
+                mesh_material_indices,
+                mesh_skins,
+                meshes,
+                skinned_entries,
+                rigid_batch_entries,
+                pickable_entries,
+                child,
+            )
+        }));
 
         let matrix = &node.transformation;
         let matrix = Mat4::new([
@@ -283,10 +390,8 @@ fn fs_main(in: VertexOutput) -> FragmentOutput {
         ]);
 
         let object = if node.meshes.len() == 1 {
-            let mut mesh_renderer = MeshRenderer::new();
-            mesh_renderer.set_material(materials.get(node.name.as_str()).unwrap().clone());
-            mesh_renderer.set_mesh(meshes.get(&node.meshes[0]).unwrap().clone(), device);
-
+            let mesh_index = node.meshes[0];
+            let mesh_handle = meshes.get(&mesh_index).unwrap().clone();
             let transform = Transform::from_mat4(&matrix);
 
             println!("{}", node.name);
@@ -298,7 +403,26 @@ fn fs_main(in: VertexOutput) -> FragmentOutput {
                 Some(node.name.to_owned()),
                 Some(transform),
             );
-            builder.with(mesh_renderer).build();
+
+            match mesh_skins.get(&mesh_index) {
+                Some(bones) => {
+                    let material_index = mesh_material_indices.get(&mesh_index).unwrap();
+                    let mut mesh_renderer = SkinnedMeshRenderer::new(bones.names.len());
+                    if let Some(material) = skinned_materials.get(material_index) {
+                        mesh_renderer.set_material(material.clone());
+                    }
+                    mesh_renderer.set_mesh(mesh_handle, device);
+                    builder.with(mesh_renderer).build();
+                    skinned_entries.push((object.clone(), bones.clone()));
+                    pickable_entries.push((object.clone(), mesh_index));
+                }
+                None => {
+                    let material_index = *mesh_material_indices.get(&mesh_index).unwrap();
+                    builder.build();
+                    rigid_batch_entries.push((object.clone(), mesh_index, material_index));
+                    pickable_entries.push((object.clone(), mesh_index));
+                }
+            }
 
             object
         } else {
@@ -319,9 +443,8 @@ fn fs_main(in: VertexOutput) -> FragmentOutput {
             for (index, &mesh) in node.meshes.iter().enumerate() {
                 println!("{}", node.name);
 
-                let mut mesh_renderer = MeshRenderer::new();
-                mesh_renderer.set_material(materials.get(node.name.as_str()).unwrap().clone());
-                mesh_renderer.set_mesh(meshes.get(&mesh).unwrap().clone(), device);
+                let material_index = mesh_material_indices.get(&mesh).unwrap();
+                let mesh_handle = meshes.get(&mesh).unwrap().clone();
 
                 let mesh_object = {
                     let mut world = use_context().world_mut();
@@ -331,7 +454,25 @@ fn fs_main(in: VertexOutput) -> FragmentOutput {
                         Some(format!("{}-mesh-{}", node.name, index)),
                         None,
                     );
-                    builder.with(mesh_renderer).build();
+
+                    match mesh_skins.get(&mesh) {
+                        Some(bones) => {
+                            let mut mesh_renderer = SkinnedMeshRenderer::new(bones.names.len());
+                            if let Some(material) = skinned_materials.get(material_index) {
+                                mesh_renderer.set_material(material.clone());
+                            }
+                            mesh_renderer.set_mesh(mesh_handle, device);
+                            builder.with(mesh_renderer).build();
+                            skinned_entries.push((mesh_object.clone(), bones.clone()));
+                            pickable_entries.push((mesh_object.clone(), mesh));
+                        }
+                        None => {
+                            builder.build();
+                            rigid_batch_entries.push((mesh_object.clone(), mesh, *material_index));
+                            pickable_entries.push((mesh_object.clone(), mesh));
+                        }
+                    }
+
                     mesh_object
                 };
 
@@ -348,15 +489,59 @@ fn fs_main(in: VertexOutput) -> FragmentOutput {
         object
     }
 
+    let mut skinned_entries = Vec::new();
+    let mut rigid_batch_entries = Vec::new();
+    let mut pickable_entries = Vec::new();
     deploy_parts(
         &ctx.gfx_ctx().device,
         &materials,
+        &skinned_materials,
+        &mesh_material_indices,
+        &mesh_skins,
         &meshes,
+        &mut skinned_entries,
+        &mut rigid_batch_entries,
+        &mut pickable_entries,
         &scene.root.unwrap(),
     );
 
+    let pickable_meshes: Vec<PickableMesh> = pickable_entries
+        .into_iter()
+        .map(|(object, mesh_index)| {
+            let (vertices, triangles) = mesh_geometry.get(&mesh_index).unwrap().clone();
+            PickableMesh::new(object, vertices, triangles)
+        })
+        .collect();
+
+    // Rigid parts are resolved into instance batches only now, once the whole
+    // node hierarchy (and thus every object's `world_matrix()`) is wired up.
+    let mut instance_batches = InstanceBatches::new();
+    for (object, mesh_index, material_index) in rigid_batch_entries {
+        let Some(material) = materials.get(&material_index) else {
+            continue;
+        };
+        let mesh_handle = meshes.get(&mesh_index).unwrap().clone();
+        let world_matrix = object.component::<TransformComponent>().world_matrix();
+        instance_batches.push_instance(
+            &ctx.gfx_ctx().device,
+            mesh_index,
+            material_index,
+            mesh_handle,
+            material.clone(),
+            world_matrix,
+        );
+    }
+
+    let mut orbit_camera = OrbitCamera::new(eye, target);
+    let mut picking_controller = PickingController::new();
+
     ctx.event_mgr()
-        .add_handler(EventHandler::<event_types::Update>::new(|_| update()));
+        .add_handler(EventHandler::<event_types::Update>::new(move |_| {
+            update_skinned_renderers(&skinned_entries);
+            update();
+            orbit_camera.update();
+            picking_controller.update(&pickable_meshes);
+        }));
 }
 
 fn update() {
@@ -0,0 +1,130 @@
+//! Builds materials straight from an assimp `Scene` instead of a hand-maintained
+//! node-name-to-texture-path table, so arbitrary imported models "just work".
+
+use r3d::{
+    gfx::{
+        BindGroupEntryResource, BindingPropKey, Material, MaterialHandle, ShaderHandle, Texture,
+    },
+    image::{self, DynamicImage},
+    russimp::{material::TextureType, scene::Scene},
+    wgpu::TextureFormat,
+    ContextHandle,
+};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// Decodes each entry in `scene.materials`' diffuse/base-color image exactly
+/// once, keyed by the material's index within that list (the same index every
+/// `Mesh::material_index` refers to).
+///
+/// The diffuse/base-color texture is resolved in the following order:
+/// - an embedded texture (`scene.textures`), when the material points at one via
+///   the `*N` embedded-texture convention assimp uses for the texture path, or
+/// - a texture file on disk, resolved relative to `model_dir`.
+///
+/// Materials without a usable diffuse texture are skipped; a model is allowed to
+/// reference more submeshes than textured materials (e.g. collision helpers).
+/// Decoding once here, instead of once per shader variant a material is bound
+/// to, means callers that build several [`MaterialHandle`]s from the same
+/// image (e.g. a lit and a skinned variant) only pay the disk read and decode
+/// once; pair with [`upload_material_textures`] to share the GPU upload too.
+pub fn import_material_images(scene: &Scene, model_dir: &Path) -> HashMap<u32, DynamicImage> {
+    let mut images = HashMap::new();
+
+    for (index, material) in scene.materials.iter().enumerate() {
+        let Some(textures) = material
+            .textures
+            .get(&TextureType::Diffuse)
+            .or_else(|| material.textures.get(&TextureType::BaseColor))
+        else {
+            continue;
+        };
+        let Some(texture) = textures.first() else {
+            continue;
+        };
+        let texture_path = texture.borrow().path.clone();
+
+        let image = match embedded_texture_index(&texture_path) {
+            Some(embedded_index) => {
+                let Some(embedded) = scene.textures.get(embedded_index) else {
+                    continue;
+                };
+                let Ok(image) = image::load_from_memory(&embedded.data) else {
+                    continue;
+                };
+                image
+            }
+            None => {
+                let path = resolve_texture_path(model_dir, &texture_path);
+                let Ok(image) = image::open(path) else {
+                    continue;
+                };
+                image
+            }
+        };
+
+        images.insert(index as u32, image.flipv());
+    }
+
+    images
+}
+
+/// Uploads each of `images` to the GPU exactly once, so material variants
+/// that bind the same diffuse image (e.g. a lit and a skinned shader) can
+/// share one [`Texture`] instead of re-uploading it per variant.
+pub fn upload_material_textures(
+    ctx: &ContextHandle,
+    images: &HashMap<u32, DynamicImage>,
+) -> HashMap<u32, Texture> {
+    images
+        .iter()
+        .map(|(&index, image)| {
+            let texture = Texture::from_image(
+                TextureFormat::Rgba8UnormSrgb,
+                image,
+                &ctx.gfx_ctx().device,
+                &ctx.gfx_ctx().queue,
+            );
+            (index, texture)
+        })
+        .collect()
+}
+
+/// Parses assimp's `*N` embedded-texture reference convention into the index
+/// into `scene.textures`, or `None` if `path` names a real file on disk.
+fn embedded_texture_index(path: &str) -> Option<usize> {
+    path.strip_prefix('*')?.parse().ok()
+}
+
+fn resolve_texture_path(model_dir: &Path, texture_path: &str) -> PathBuf {
+    let texture_path = texture_path.replace('\\', "/");
+    model_dir.join(texture_path)
+}
+
+/// Builds a material bound to `shader` that only exposes the diffuse
+/// texture/sampler pair, for shader variants with no further material bindings
+/// (e.g. the skinned path).
+pub fn create_textured_material(
+    ctx: &ContextHandle,
+    shader: &ShaderHandle,
+    texture: &Texture,
+) -> MaterialHandle {
+    let mut render_mgr = ctx.render_mgr_mut();
+    let mut material = Material::new(shader.clone(), render_mgr.pipeline_layout_cache());
+    material.set_bind_property(
+        &BindingPropKey::StringKey("texture".to_owned()),
+        BindGroupEntryResource::TextureView {
+            texture_view: texture.view.clone(),
+        },
+    );
+    material.set_bind_property(
+        &BindingPropKey::StringKey("texture_sampler".to_owned()),
+        BindGroupEntryResource::Sampler {
+            sampler: texture.sampler.clone(),
+        },
+    );
+    material.update_bind_group(&ctx.gfx_ctx().device);
+    MaterialHandle::new(material)
+}
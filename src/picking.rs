@@ -0,0 +1,263 @@
+//! Mouse-click ray picking: unprojects the cursor into a world-space ray and
+//! tests it against every pickable mesh part, first by a world-space AABB
+//! (broad phase), then by Möller–Trumbore ray/triangle intersection against
+//! the mesh's triangles in the object's local space (narrow phase).
+
+use r3d::{
+    gfx::Camera,
+    input::InputDevice,
+    math::{Mat4, Vec3},
+    object::ObjectHandle,
+    transform::TransformComponent,
+    use_context,
+};
+
+const EPSILON: f32 = 1e-6;
+
+/// Fired through `event_mgr` when a click picks a mesh part.
+pub struct Picked {
+    pub object: ObjectHandle,
+    pub triangle_index: u32,
+    pub point: Vec3,
+}
+
+/// One mesh part's triangles in local space, paired with the object whose
+/// transform places them in the world.
+pub struct PickableMesh {
+    pub object: ObjectHandle,
+    vertices: Vec<Vec3>,
+    triangles: Vec<[u32; 3]>,
+    local_min: Vec3,
+    local_max: Vec3,
+}
+
+impl PickableMesh {
+    pub fn new(object: ObjectHandle, vertices: Vec<Vec3>, triangles: Vec<[u32; 3]>) -> Self {
+        let mut local_min = vertices[0];
+        let mut local_max = vertices[0];
+        for &vertex in &vertices[1..] {
+            local_min = Vec3::new(
+                local_min.x.min(vertex.x),
+                local_min.y.min(vertex.y),
+                local_min.z.min(vertex.z),
+            );
+            local_max = Vec3::new(
+                local_max.x.max(vertex.x),
+                local_max.y.max(vertex.y),
+                local_max.z.max(vertex.z),
+            );
+        }
+
+        PickableMesh {
+            object,
+            vertices,
+            triangles,
+            local_min,
+            local_max,
+        }
+    }
+}
+
+/// Tracks left-mouse-button edges so a held click only picks once.
+#[derive(Default)]
+pub struct PickingController {
+    was_left_down: bool,
+}
+
+impl PickingController {
+    pub fn new() -> Self {
+        PickingController::default()
+    }
+
+    /// Call once per frame; on the frame the left mouse button goes down,
+    /// casts a pick ray through the cursor and fires a [`Picked`] event for
+    /// the nearest hit among `meshes`, if any.
+    pub fn update(&mut self, meshes: &[PickableMesh]) {
+        let ctx = use_context();
+        let input_mgr = ctx.input_mgr();
+        let mouse = input_mgr.mouse();
+
+        let left_down = mouse.input("left").unwrap().value > 0.0;
+        let clicked = left_down && !self.was_left_down;
+        self.was_left_down = left_down;
+        if !clicked {
+            return;
+        }
+
+        let cursor_x = mouse.input("cursor_x").unwrap().value;
+        let cursor_y = mouse.input("cursor_y").unwrap().value;
+        let ndc_x = (cursor_x / 800.0) * 2.0 - 1.0;
+        let ndc_y = 1.0 - (cursor_y / 600.0) * 2.0;
+
+        if let Some((object, triangle_index, point)) = pick(meshes, ndc_x, ndc_y) {
+            ctx.event_mgr().fire(Picked {
+                object,
+                triangle_index,
+                point,
+            });
+        }
+    }
+}
+
+fn pick(meshes: &[PickableMesh], ndc_x: f32, ndc_y: f32) -> Option<(ObjectHandle, u32, Vec3)> {
+    let ctx = use_context();
+    let camera_object = ctx.object_mgr().find("camera").unwrap();
+    let camera_world = camera_object
+        .component::<TransformComponent>()
+        .world_matrix();
+    let projection = camera_object.component::<Camera>().projection_matrix();
+
+    let inverse_projection = projection.inverse();
+    let near_view = inverse_projection.transform_point3(Vec3::new(ndc_x, ndc_y, 0.0));
+    let far_view = inverse_projection.transform_point3(Vec3::new(ndc_x, ndc_y, 1.0));
+
+    let origin = camera_world.transform_point3(near_view);
+    let target = camera_world.transform_point3(far_view);
+    let direction = (target - origin).normalized();
+
+    // Tracked by squared world-space distance from `origin` rather than the
+    // raw local-space `t`: `local_direction` is renormalized per mesh, which
+    // discards that object's scale, so a bare `t` comparison isn't comparable
+    // across meshes with different scale.
+    let mut nearest: Option<(f32, ObjectHandle, u32, Vec3)> = None;
+
+    for mesh in meshes {
+        let world_matrix = mesh.object.component::<TransformComponent>().world_matrix();
+        let (world_min, world_max) = transform_aabb(mesh.local_min, mesh.local_max, &world_matrix);
+        if !ray_intersects_aabb(origin, direction, world_min, world_max) {
+            continue;
+        }
+
+        let inverse_world = world_matrix.inverse();
+        let local_origin = inverse_world.transform_point3(origin);
+        let local_direction = inverse_world.transform_vector3(direction).normalized();
+
+        for (triangle_index, triangle) in mesh.triangles.iter().enumerate() {
+            let a = mesh.vertices[triangle[0] as usize];
+            let b = mesh.vertices[triangle[1] as usize];
+            let c = mesh.vertices[triangle[2] as usize];
+
+            let Some(t) = moller_trumbore(local_origin, local_direction, a, b, c) else {
+                continue;
+            };
+
+            let local_point = local_origin + local_direction * t;
+            let world_point = world_matrix.transform_point3(local_point);
+            let distance_squared = (world_point - origin).length_squared();
+
+            let is_nearer = match &nearest {
+                Some((nearest_distance_squared, ..)) => {
+                    distance_squared < *nearest_distance_squared
+                }
+                None => true,
+            };
+            if is_nearer {
+                nearest = Some((
+                    distance_squared,
+                    mesh.object.clone(),
+                    triangle_index as u32,
+                    world_point,
+                ));
+            }
+        }
+    }
+
+    nearest.map(|(_, object, triangle_index, point)| (object, triangle_index, point))
+}
+
+fn transform_aabb(min: Vec3, max: Vec3, matrix: &Mat4) -> (Vec3, Vec3) {
+    let corners = [
+        Vec3::new(min.x, min.y, min.z),
+        Vec3::new(max.x, min.y, min.z),
+        Vec3::new(min.x, max.y, min.z),
+        Vec3::new(max.x, max.y, min.z),
+        Vec3::new(min.x, min.y, max.z),
+        Vec3::new(max.x, min.y, max.z),
+        Vec3::new(min.x, max.y, max.z),
+        Vec3::new(max.x, max.y, max.z),
+    ];
+
+    let mut world_min = matrix.transform_point3(corners[0]);
+    let mut world_max = world_min;
+    for &corner in &corners[1..] {
+        let world_corner = matrix.transform_point3(corner);
+        world_min = Vec3::new(
+            world_min.x.min(world_corner.x),
+            world_min.y.min(world_corner.y),
+            world_min.z.min(world_corner.z),
+        );
+        world_max = Vec3::new(
+            world_max.x.max(world_corner.x),
+            world_max.y.max(world_corner.y),
+            world_max.z.max(world_corner.z),
+        );
+    }
+
+    (world_min, world_max)
+}
+
+fn ray_intersects_aabb(origin: Vec3, direction: Vec3, min: Vec3, max: Vec3) -> bool {
+    let mut t_min = f32::NEG_INFINITY;
+    let mut t_max = f32::INFINITY;
+
+    for axis in 0..3 {
+        let (origin_axis, direction_axis, min_axis, max_axis) = match axis {
+            0 => (origin.x, direction.x, min.x, max.x),
+            1 => (origin.y, direction.y, min.y, max.y),
+            _ => (origin.z, direction.z, min.z, max.z),
+        };
+
+        if direction_axis.abs() < EPSILON {
+            if origin_axis < min_axis || origin_axis > max_axis {
+                return false;
+            }
+            continue;
+        }
+
+        let inverse_direction = 1.0 / direction_axis;
+        let mut t1 = (min_axis - origin_axis) * inverse_direction;
+        let mut t2 = (max_axis - origin_axis) * inverse_direction;
+        if t1 > t2 {
+            std::mem::swap(&mut t1, &mut t2);
+        }
+        t_min = t_min.max(t1);
+        t_max = t_max.min(t2);
+        if t_min > t_max {
+            return false;
+        }
+    }
+
+    t_max >= 0.0
+}
+
+/// Möller–Trumbore ray/triangle intersection; returns the ray parameter `t`
+/// of the nearest forward intersection, or `None` if the ray misses.
+fn moller_trumbore(origin: Vec3, direction: Vec3, a: Vec3, b: Vec3, c: Vec3) -> Option<f32> {
+    let edge1 = b - a;
+    let edge2 = c - a;
+    let h = direction.cross(edge2);
+    let det = edge1.dot(h);
+    if det.abs() < EPSILON {
+        return None;
+    }
+
+    let inverse_det = 1.0 / det;
+    let s = origin - a;
+    let u = inverse_det * s.dot(h);
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let q = s.cross(edge1);
+    let v = inverse_det * direction.dot(q);
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let t = inverse_det * edge2.dot(q);
+    if t > EPSILON {
+        Some(t)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,211 @@
+//! Directional light-space depth pre-pass: builds the shadow map render target
+//! and the light's view-projection matrix the main pass samples through a
+//! comparison sampler. The projection is orthographic, matching a directional
+//! (parallel-ray) light.
+
+use bytemuck::{Pod, Zeroable};
+use r3d::{
+    gfx::DepthPrepass,
+    math::Vec3,
+    wgpu::{self, CompareFunction, Device, FilterMode, SamplerDescriptor, TextureFormat},
+    ContextHandle,
+};
+
+/// Shadow map resolution and the world-space bounds of the light's
+/// orthographic projection.
+pub struct ShadowConfig {
+    pub resolution: u32,
+    pub half_extent: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Default for ShadowConfig {
+    fn default() -> Self {
+        ShadowConfig {
+            resolution: 2048,
+            half_extent: 3.0,
+            near: 0.1,
+            far: 20.0,
+        }
+    }
+}
+
+pub struct ShadowMap {
+    pub depth_view: wgpu::TextureView,
+    pub comparison_sampler: wgpu::Sampler,
+    /// Column-major, ready to upload as a `mat4x4<f32>` uniform.
+    pub light_view_proj: [f32; 16],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Mat4Uniform([f32; 16]);
+
+/// Builds the shadow map's depth target and the light-space view-projection
+/// matrix for a directional light traveling along `direction`, framed around
+/// `target` by `config`'s orthographic bounds.
+pub fn create_shadow_map(
+    device: &Device,
+    config: &ShadowConfig,
+    direction: Vec3,
+    target: Vec3,
+) -> ShadowMap {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("shadow_map"),
+        size: wgpu::Extent3d {
+            width: config.resolution,
+            height: config.resolution,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: TextureFormat::Depth32Float,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    });
+    let depth_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    let comparison_sampler = device.create_sampler(&SamplerDescriptor {
+        label: Some("shadow_comparison_sampler"),
+        compare: Some(CompareFunction::LessEqual),
+        mag_filter: FilterMode::Linear,
+        min_filter: FilterMode::Linear,
+        ..Default::default()
+    });
+
+    let light_position = target - direction.normalized() * ((config.near + config.far) * 0.5);
+    let view = look_at(light_position, target, Vec3::UP);
+    let half = config.half_extent;
+    let projection = orthographic(-half, half, -half, half, config.near, config.far);
+
+    ShadowMap {
+        depth_view,
+        comparison_sampler,
+        light_view_proj: mat4_mul(&projection, &view),
+    }
+}
+
+pub fn create_light_view_proj_buffer(device: &Device, light_view_proj: [f32; 16]) -> wgpu::Buffer {
+    use r3d::wgpu::util::DeviceExt;
+
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("light_view_proj"),
+        contents: bytemuck::bytes_of(&Mat4Uniform(light_view_proj)),
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+    })
+}
+
+/// Registers `shadow_map` as a depth-only pass the renderer runs before the
+/// main pass, rendering every `MeshRenderer`/`SkinnedMeshRenderer` from the
+/// light's point of view.
+pub fn register_depth_prepass(ctx: &ContextHandle, shadow_map: &ShadowMap) {
+    ctx.render_mgr_mut().add_depth_prepass(DepthPrepass {
+        depth_view: shadow_map.depth_view.clone(),
+        view_proj: shadow_map.light_view_proj,
+    });
+}
+
+fn look_at(eye: Vec3, target: Vec3, up: Vec3) -> [f32; 16] {
+    let forward = (target - eye).normalized();
+    // A view straight along `up` (e.g. a light pointing straight down) has no
+    // defined right axis against it, so another reference axis is used there.
+    let up = if forward.cross(up).length_squared() < 1e-6 {
+        Vec3::RIGHT
+    } else {
+        up
+    };
+    let right = forward.cross(up).normalized();
+    let up = right.cross(forward);
+
+    [
+        right.x,
+        up.x,
+        -forward.x,
+        0.0,
+        right.y,
+        up.y,
+        -forward.y,
+        0.0,
+        right.z,
+        up.z,
+        -forward.z,
+        0.0,
+        -right.dot(eye),
+        -up.dot(eye),
+        forward.dot(eye),
+        1.0,
+    ]
+}
+
+fn orthographic(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> [f32; 16] {
+    let sx = 2.0 / (right - left);
+    let sy = 2.0 / (top - bottom);
+    let sz = -1.0 / (far - near);
+    let tx = -(right + left) / (right - left);
+    let ty = -(top + bottom) / (top - bottom);
+    let tz = -near / (far - near);
+
+    #[rustfmt::skip]
+    let matrix = [
+        sx,  0.0, 0.0, 0.0,
+        0.0, sy,  0.0, 0.0,
+        0.0, 0.0, sz,  0.0,
+        tx,  ty,  tz,  1.0,
+    ];
+    matrix
+}
+
+/// Multiplies two column-major 4x4 matrices as `a * b`.
+fn mat4_mul(a: &[f32; 16], b: &[f32; 16]) -> [f32; 16] {
+    let mut out = [0.0f32; 16];
+    for col in 0..4 {
+        for row in 0..4 {
+            let mut sum = 0.0;
+            for k in 0..4 {
+                sum += a[k * 4 + row] * b[col * 4 + k];
+            }
+            out[col * 4 + row] = sum;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A point `near` in front of the eye along `target` should land at
+    /// `zndc == 0`, and a point `far` in front should land at `zndc == 1`
+    /// (wgpu's `0..1` depth range) — not the reverse.
+    #[test]
+    fn light_view_proj_maps_near_to_0_and_far_to_1() {
+        let eye = Vec3::new(0.0, 5.0, 0.0);
+        let target = Vec3::new(0.0, 0.0, 0.0);
+        let near = 0.1;
+        let far = 20.0;
+
+        let view = look_at(eye, target, Vec3::UP);
+        let projection = orthographic(-3.0, 3.0, -3.0, 3.0, near, far);
+        let view_proj = mat4_mul(&projection, &view);
+
+        let forward = (target - eye).normalized();
+        let near_point = eye + forward * near;
+        let far_point = eye + forward * far;
+
+        assert!((transform_z(&view_proj, near_point)).abs() < 1e-5);
+        assert!((transform_z(&view_proj, far_point) - 1.0).abs() < 1e-5);
+    }
+
+    /// Applies a column-major 4x4 matrix to a point and returns its resulting
+    /// (perspective-divided) z, mirroring what the shader does with `gl_Position.z`.
+    fn transform_z(matrix: &[f32; 16], point: Vec3) -> f32 {
+        let x = point.x;
+        let y = point.y;
+        let z = point.z;
+        let w_out = matrix[3] * x + matrix[7] * y + matrix[11] * z + matrix[15];
+        let z_out = matrix[2] * x + matrix[6] * y + matrix[10] * z + matrix[14];
+        z_out / w_out
+    }
+}
@@ -0,0 +1,115 @@
+//! CPU-side preparation for GPU skinning: turns assimp bone data into the
+//! per-vertex bone indices/weights `r3d::gfx::Mesh` uploads as vertex attributes,
+//! plus the bind-pose bone list needed to rebuild the bone palette every frame
+//! from the live object hierarchy.
+
+use r3d::{
+    gfx::{SkinnedMeshRenderer, VertexSkin},
+    math::Mat4,
+    object::ObjectHandle,
+    russimp::mesh::Mesh as AssimpMesh,
+    transform::TransformComponent,
+    use_context,
+};
+
+/// Size of the `bone_palette` array the skinned shader declares in `main.rs`;
+/// bone indices beyond this are out of range for the shader's fixed array.
+const MAX_BONES: usize = 128;
+
+/// A mesh's bones in bind pose: each bone's name, used to look up its current
+/// world transform in the object hierarchy that mirrors the node tree, and its
+/// inverse-bind (offset) matrix.
+#[derive(Clone)]
+pub struct SkinBones {
+    pub names: Vec<String>,
+    pub offsets: Vec<Mat4>,
+}
+
+/// Builds per-vertex skin weights for `mesh`, or `None` if it has no bones, in
+/// which case the caller should fall back to rigid rendering.
+pub fn build_vertex_skin(mesh: &AssimpMesh) -> Option<(VertexSkin, SkinBones)> {
+    if mesh.bones.is_empty() {
+        return None;
+    }
+
+    if mesh.bones.len() > MAX_BONES {
+        eprintln!(
+            "mesh has {} bones, exceeding the shader's bone_palette size of {MAX_BONES}; \
+             skin deformation will be incorrect for vertices weighted to the excess bones",
+            mesh.bones.len(),
+        );
+    }
+
+    let mut influences = vec![Vec::new(); mesh.vertices.len()];
+    let mut names = Vec::with_capacity(mesh.bones.len());
+    let mut offsets = Vec::with_capacity(mesh.bones.len());
+
+    for (bone_index, bone) in mesh.bones.iter().enumerate() {
+        names.push(bone.name.clone());
+        offsets.push(convert_matrix(&bone.offset_matrix));
+
+        for weight in &bone.weights {
+            influences[weight.vertex_id as usize].push((bone_index as u32, weight.weight));
+        }
+    }
+
+    let mut bone_indices = Vec::with_capacity(influences.len());
+    let mut bone_weights = Vec::with_capacity(influences.len());
+    for mut vertex_influences in influences {
+        vertex_influences.sort_by(|a, b| b.1.total_cmp(&a.1));
+        vertex_influences.truncate(4);
+
+        let total: f32 = vertex_influences.iter().map(|(_, weight)| *weight).sum();
+        let mut indices = [0u32; 4];
+        let mut weights = [0.0f32; 4];
+        for (slot, (bone_index, weight)) in vertex_influences.into_iter().enumerate() {
+            indices[slot] = bone_index;
+            weights[slot] = if total > 0.0 { weight / total } else { 0.0 };
+        }
+
+        bone_indices.push(indices);
+        bone_weights.push(weights);
+    }
+
+    Some((
+        VertexSkin {
+            bone_indices,
+            bone_weights,
+        },
+        SkinBones { names, offsets },
+    ))
+}
+
+/// Recomputes the bone palette for every entry and uploads it to its renderer.
+/// Called once per frame so bones that are animated through the object
+/// hierarchy (by moving the node objects `deploy_parts` created) deform the mesh.
+pub fn update_skinned_renderers(entries: &[(ObjectHandle, SkinBones)]) {
+    let ctx = use_context();
+    let object_mgr = ctx.object_mgr();
+    let queue = &ctx.gfx_ctx().queue;
+
+    for (object, bones) in entries {
+        let palette = bones
+            .names
+            .iter()
+            .zip(&bones.offsets)
+            .map(|(name, offset)| match object_mgr.find(name) {
+                Some(bone_object) => {
+                    bone_object.component::<TransformComponent>().world_matrix() * *offset
+                }
+                None => *offset,
+            })
+            .collect::<Vec<_>>();
+
+        object
+            .component::<SkinnedMeshRenderer>()
+            .set_bone_palette(&palette, queue);
+    }
+}
+
+fn convert_matrix(matrix: &r3d::russimp::Matrix4x4) -> Mat4 {
+    Mat4::new([
+        matrix.a1, matrix.b1, matrix.c1, matrix.d1, matrix.a2, matrix.b2, matrix.c2, matrix.d2,
+        matrix.a3, matrix.b3, matrix.c3, matrix.d3, matrix.a4, matrix.b4, matrix.c4, matrix.d4,
+    ])
+}